@@ -0,0 +1,100 @@
+use std::io::{Read, Write};
+
+use crate::ir::{BfOp, BfOpKind};
+
+use super::Backend;
+
+const TAPE_SIZE: usize = 500000;
+
+/// Checks `ptr` against the tape's `[0, TAPE_SIZE)` bounds, trapping with
+/// the same message and exit code `X64Backend` uses, so both backends fail
+/// a runaway `>`/`<` the same documented way. Returns the in-bounds `ptr`
+/// as a `usize` for indexing.
+fn check_bounds(ptr: i64, op_index: usize) -> usize {
+    if ptr < 0 || ptr >= TAPE_SIZE as i64 {
+        eprintln!("error: tape pointer out of bounds at op {}", op_index);
+        std::process::exit(1);
+    }
+    ptr as usize
+}
+
+/// Pure-Rust backend that walks `ops` with a program counter, honoring the
+/// same fused ops the JIT understands. Slower than `X64Backend`, but runs
+/// anywhere `rustc` does.
+pub struct Interpreter;
+
+impl Backend for Interpreter {
+    fn run(&mut self, ops: &[BfOp], max_steps: Option<u64>) {
+        let mut tape = vec![0u8; TAPE_SIZE];
+        let mut ptr: i64 = 0;
+        let mut pc: usize = 0;
+        let mut steps_left = max_steps;
+        let stdin = std::io::stdin();
+        let stdout = std::io::stdout();
+
+        while pc < ops.len() {
+            if let Some(steps) = steps_left.as_mut() {
+                if *steps == 0 {
+                    eprintln!("error: step limit exceeded at op {}", pc);
+                    std::process::exit(1);
+                }
+                *steps -= 1;
+            }
+            let op = &ops[pc];
+            match op.kind {
+                BfOpKind::IncPtr => ptr += op.argument as i64,
+                BfOpKind::DecPtr => ptr -= op.argument as i64,
+                BfOpKind::IncData => {
+                    let p = check_bounds(ptr, pc);
+                    tape[p] = tape[p].wrapping_add(op.argument as u8);
+                }
+                BfOpKind::DecData => {
+                    let p = check_bounds(ptr, pc);
+                    tape[p] = tape[p].wrapping_sub(op.argument as u8);
+                }
+                BfOpKind::WriteStdout => {
+                    let p = check_bounds(ptr, pc);
+                    stdout.lock().write_all(&tape[p..p + 1]).unwrap();
+                }
+                BfOpKind::ReadStdin => {
+                    let p = check_bounds(ptr, pc);
+                    stdin.lock().read_exact(&mut tape[p..p + 1]).unwrap();
+                }
+                BfOpKind::LoopSetToZero => {
+                    let p = check_bounds(ptr, pc);
+                    tape[p] = 0;
+                }
+                BfOpKind::LoopMovePtr => loop {
+                    let p = check_bounds(ptr, pc);
+                    if tape[p] == 0 {
+                        break;
+                    }
+                    ptr += op.argument as i64;
+                },
+                BfOpKind::LoopMoveData => {
+                    let p = check_bounds(ptr, pc);
+                    if tape[p] != 0 {
+                        let dst = check_bounds(ptr + op.argument as i64, pc);
+                        tape[dst] = tape[dst].wrapping_add(tape[p]);
+                        tape[p] = 0;
+                    }
+                }
+                BfOpKind::JumpIfDataZero => {
+                    let p = check_bounds(ptr, pc);
+                    if tape[p] == 0 {
+                        pc = op.argument as usize;
+                        continue;
+                    }
+                }
+                BfOpKind::JumpIfDataNotZero => {
+                    let p = check_bounds(ptr, pc);
+                    if tape[p] != 0 {
+                        pc = op.argument as usize;
+                        continue;
+                    }
+                }
+            }
+            pc += 1;
+        }
+    }
+}
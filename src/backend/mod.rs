@@ -0,0 +1,31 @@
+pub mod interpreter;
+pub mod x64;
+
+use crate::ir::BfOp;
+
+/// Executes a translated Brainfuck program.
+///
+/// Implementations are free to interpret `ops` directly or compile them to
+/// native code; callers only rely on the observable side effects (stdin,
+/// stdout) produced by `run`. `max_steps` bounds how many ops (interpreter)
+/// or loop back-edges (JIT) the program may execute before it's aborted;
+/// `None` preserves the historical unlimited behavior.
+pub trait Backend {
+    fn run(&mut self, ops: &[BfOp], max_steps: Option<u64>);
+}
+
+pub use interpreter::Interpreter;
+pub use x64::X64Backend;
+
+/// Picks `X64Backend` on x86-64 targets and the interpreter elsewhere.
+///
+/// x86-64 is the only architecture `X64Backend` emits machine code for, so
+/// every other target falls back to the portable interpreter. Only the
+/// architecture is checked, not the OS.
+pub fn default_backend() -> Box<dyn Backend> {
+    if cfg!(target_arch = "x86_64") {
+        Box::new(X64Backend)
+    } else {
+        Box::new(Interpreter)
+    }
+}
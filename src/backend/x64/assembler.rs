@@ -0,0 +1,383 @@
+/// A position in the emitted code that isn't known yet when a jump to it is
+/// written. `bind` fixes its final offset; `jcc` can reference it before or
+/// after that happens.
+#[derive(Clone, Copy)]
+pub struct Label(usize);
+
+pub enum Cond {
+    Zero,
+    NotZero,
+    Below,
+    AboveOrEqual,
+}
+
+/// x86-64 code buffer with semantic emitters for the handful of
+/// instructions the Brainfuck JIT needs, plus a label API so callers never
+/// compute jump offsets by hand.
+///
+/// The tape cursor lives in `r13` throughout; `r12` and `r15` hold the
+/// tape's lower and (exclusive) upper bound for the bounds-checked mode,
+/// `r14` is used as scratch by the fused loop ops, and `rbx` holds the
+/// remaining step budget when one is configured. `rbx` is used rather than
+/// `r11` because `syscall` hardware-clobbers `rcx`/`r11` for `sysret`, which
+/// would silently defeat the budget on any op that performs I/O.
+///
+/// `rbx` and `r12`-`r15` are callee-saved under the System V x86-64 ABI, so
+/// `compile` pushes all five at entry and pops them before every `ret` —
+/// otherwise the generated code would silently corrupt any value the caller
+/// kept live in one of them across the call.
+pub struct Assembler {
+    content: Vec<u8>,
+    labels: Vec<Option<usize>>,
+    fixups: Vec<(usize, Label)>,
+    instr_ends: Vec<usize>,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self {
+            content: vec![],
+            labels: vec![],
+            fixups: vec![],
+            instr_ends: vec![],
+        }
+    }
+
+    fn emit_byte(&mut self, b: u8) {
+        self.content.push(b);
+    }
+
+    fn emit_bytes(&mut self, bs: &[u8]) {
+        self.content.extend_from_slice(bs);
+    }
+
+    /// Marks the current position as the end of one real x86-64 instruction,
+    /// so `instruction_ends` can report where each one starts and stops for
+    /// tooling (the `asm` listing) without having to re-decode the bytes.
+    fn mark(&mut self) {
+        self.instr_ends.push(self.content.len());
+    }
+
+    /// The end offset (exclusive) of every instruction emitted so far, in
+    /// emission order.
+    pub fn instruction_ends(&self) -> Vec<usize> {
+        self.instr_ends.clone()
+    }
+
+    fn emit_u16(&mut self, n: u16) {
+        self.emit_bytes(&[n as u8 & 0xFF, (n >> 8) as u8 & 0xFF])
+    }
+
+    fn emit_u32(&mut self, n: u32) {
+        self.emit_u16((n & 0xFFFF as u32) as u16);
+        self.emit_u16((n >> 16 & 0xFFFF as u32) as u16);
+    }
+
+    fn emit_u64(&mut self, n: u64) {
+        self.emit_u32((n & 0xFFFFFFFF as u64) as u32);
+        self.emit_u32((n >> 32 & 0xFFFFFFFF as u64) as u32);
+    }
+
+    fn replace_u32(&mut self, start: usize, n: u32) {
+        self.content[start..start + 4].copy_from_slice(&n.to_le_bytes());
+    }
+
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    /// `mov r13, imm64` — loads the tape cursor.
+    pub fn mov_r13_imm64(&mut self, addr: u64) {
+        self.emit_bytes(&[0x49, 0xBD]);
+        self.emit_u64(addr);
+        self.mark();
+    }
+
+    /// `mov r12, imm64` — loads the tape's lower bound.
+    pub fn mov_r12_imm64(&mut self, addr: u64) {
+        self.emit_bytes(&[0x49, 0xBC]);
+        self.emit_u64(addr);
+        self.mark();
+    }
+
+    /// `mov r15, imm64` — loads the tape's (exclusive) upper bound.
+    pub fn mov_r15_imm64(&mut self, addr: u64) {
+        self.emit_bytes(&[0x49, 0xBF]);
+        self.emit_u64(addr);
+        self.mark();
+    }
+
+    /// `mov eax, imm32`
+    pub fn mov_eax_imm32(&mut self, n: u32) {
+        self.emit_byte(0xB8);
+        self.emit_u32(n);
+        self.mark();
+    }
+
+    /// `mov rbx, imm64` — loads the remaining step budget.
+    pub fn mov_rbx_imm64(&mut self, n: u64) {
+        self.emit_bytes(&[0x48, 0xBB]);
+        self.emit_u64(n);
+        self.mark();
+    }
+
+    /// `sub rbx, imm8`
+    pub fn sub_rbx_imm8(&mut self, n: u8) {
+        self.emit_bytes(&[0x48, 0x83, 0xEB, n]);
+        self.mark();
+    }
+
+    /// `cmp r13, r12`
+    pub fn cmp_r13_r12(&mut self) {
+        self.emit_bytes(&[0x4D, 0x39, 0xE5]);
+        self.mark();
+    }
+
+    /// `cmp r13, r15`
+    pub fn cmp_r13_r15(&mut self) {
+        self.emit_bytes(&[0x4D, 0x39, 0xFD]);
+        self.mark();
+    }
+
+    /// `cmp r14, r12`
+    pub fn cmp_r14_r12(&mut self) {
+        self.emit_bytes(&[0x4D, 0x39, 0xE6]);
+        self.mark();
+    }
+
+    /// `cmp r14, r15`
+    pub fn cmp_r14_r15(&mut self) {
+        self.emit_bytes(&[0x4D, 0x39, 0xFE]);
+        self.mark();
+    }
+
+    /// `add r13, imm32`
+    pub fn add_r13_imm32(&mut self, n: u32) {
+        self.emit_bytes(&[0x49, 0x81, 0xc5]);
+        self.emit_u32(n);
+        self.mark();
+    }
+
+    /// `sub r13, imm32`
+    pub fn sub_r13_imm32(&mut self, n: u32) {
+        self.emit_bytes(&[0x49, 0x81, 0xed]);
+        self.emit_u32(n);
+        self.mark();
+    }
+
+    /// `add byte ptr [r13], imm8`
+    pub fn add_byte_ptr_r13_imm8(&mut self, n: u8) {
+        self.emit_bytes(&[0x41, 0x80, 0x45, 0x00, n]);
+        self.mark();
+    }
+
+    /// `add word ptr [r13], imm16`
+    pub fn add_word_ptr_r13_imm16(&mut self, n: u16) {
+        self.emit_bytes(&[0x66, 0x41, 0x81, 0x45, 0x00]);
+        self.emit_u16(n);
+        self.mark();
+    }
+
+    /// `sub byte ptr [r13], imm8`
+    pub fn sub_byte_ptr_r13_imm8(&mut self, n: u8) {
+        self.emit_bytes(&[0x41, 0x80, 0x6d, 0x00, n]);
+        self.mark();
+    }
+
+    /// `sub word ptr [r13], imm16`
+    pub fn sub_word_ptr_r13_imm16(&mut self, n: u16) {
+        self.emit_bytes(&[0x66, 0x41, 0x81, 0x6d, 0x00]);
+        self.emit_u16(n);
+        self.mark();
+    }
+
+    /// `mov byte ptr [r13], 0`
+    pub fn mov_byte_ptr_r13_zero(&mut self) {
+        self.emit_bytes(&[0x41, 0xC6, 0x45, 0x00, 0x00]);
+        self.mark();
+    }
+
+    /// `cmp byte ptr [r13], 0`
+    pub fn cmp_byte_ptr_r13_zero(&mut self) {
+        self.emit_bytes(&[0x41, 0x80, 0x7d, 0x00, 0x00]);
+        self.mark();
+    }
+
+    /// `mov r14, r13`
+    pub fn mov_r14_r13(&mut self) {
+        self.emit_bytes(&[0x4d, 0x89, 0xee]);
+        self.mark();
+    }
+
+    /// `add r14, imm32`
+    pub fn add_r14_imm32(&mut self, n: u32) {
+        self.emit_bytes(&[0x49, 0x81, 0xc6]);
+        self.emit_u32(n);
+        self.mark();
+    }
+
+    /// `sub r14, imm32`
+    pub fn sub_r14_imm32(&mut self, n: u32) {
+        self.emit_bytes(&[0x49, 0x81, 0xee]);
+        self.emit_u32(n);
+        self.mark();
+    }
+
+    /// `movzx rax, byte ptr [r13]`
+    pub fn movzx_rax_byte_ptr_r13(&mut self) {
+        self.emit_bytes(&[0x49, 0x0f, 0xb6, 0x45, 0x0]);
+        self.mark();
+    }
+
+    /// `add byte ptr [r14], al`
+    pub fn add_byte_ptr_r14_al(&mut self) {
+        self.emit_bytes(&[0x41, 0x00, 0x06]);
+        self.mark();
+    }
+
+    /// Writes stdout(1, &tape[ptr], 1) via `syscall`. Emitted (and marked)
+    /// as the five instructions it actually lowers to, so the `asm` listing
+    /// groups each one instead of treating the whole sequence as one.
+    pub fn syscall_write(&mut self) {
+        self.emit_bytes(&[0x48, 0xC7, 0xC0, 0x01, 0x00, 0x00, 0x00]); // mov rax, 1
+        self.mark();
+        self.emit_bytes(&[0x48, 0xC7, 0xC7, 0x01, 0x00, 0x00, 0x00]); // mov rdi, 1
+        self.mark();
+        self.emit_bytes(&[0x4C, 0x89, 0xEE]); // mov rsi, r13
+        self.mark();
+        self.emit_bytes(&[0x48, 0xC7, 0xC2, 0x01, 0x00, 0x00, 0x00]); // mov rdx, 1
+        self.mark();
+        self.emit_bytes(&[0x0F, 0x05]); // syscall
+        self.mark();
+    }
+
+    /// Reads stdin(0, &tape[ptr], 1) via `syscall`. Marked per-instruction
+    /// like `syscall_write`.
+    pub fn syscall_read(&mut self) {
+        self.emit_bytes(&[0x48, 0xC7, 0xC0, 0x00, 0x00, 0x00, 0x00]); // mov rax, 0
+        self.mark();
+        self.emit_bytes(&[0x48, 0xC7, 0xC7, 0x00, 0x00, 0x00, 0x00]); // mov rdi, 0
+        self.mark();
+        self.emit_bytes(&[0x4C, 0x89, 0xEE]); // mov rsi, r13
+        self.mark();
+        self.emit_bytes(&[0x48, 0xC7, 0xC2, 0x01, 0x00, 0x00, 0x00]); // mov rdx, 1
+        self.mark();
+        self.emit_bytes(&[0x0F, 0x05]); // syscall
+        self.mark();
+    }
+
+    pub fn ret(&mut self) {
+        self.emit_byte(0xc3);
+        self.mark();
+    }
+
+    /// `push rbx` — callers rely on `rbx` surviving a call under the System
+    /// V ABI, so the JIT must save it before clobbering it for the step
+    /// budget and restore it before returning.
+    pub fn push_rbx(&mut self) {
+        self.emit_byte(0x53);
+        self.mark();
+    }
+
+    /// `pop rbx`
+    pub fn pop_rbx(&mut self) {
+        self.emit_byte(0x5B);
+        self.mark();
+    }
+
+    /// `push r12` — `r12`-`r15` are callee-saved too; see `push_rbx`.
+    pub fn push_r12(&mut self) {
+        self.emit_bytes(&[0x41, 0x54]);
+        self.mark();
+    }
+
+    /// `pop r12`
+    pub fn pop_r12(&mut self) {
+        self.emit_bytes(&[0x41, 0x5C]);
+        self.mark();
+    }
+
+    /// `push r13`
+    pub fn push_r13(&mut self) {
+        self.emit_bytes(&[0x41, 0x55]);
+        self.mark();
+    }
+
+    /// `pop r13`
+    pub fn pop_r13(&mut self) {
+        self.emit_bytes(&[0x41, 0x5D]);
+        self.mark();
+    }
+
+    /// `push r14`
+    pub fn push_r14(&mut self) {
+        self.emit_bytes(&[0x41, 0x56]);
+        self.mark();
+    }
+
+    /// `pop r14`
+    pub fn pop_r14(&mut self) {
+        self.emit_bytes(&[0x41, 0x5E]);
+        self.mark();
+    }
+
+    /// `push r15`
+    pub fn push_r15(&mut self) {
+        self.emit_bytes(&[0x41, 0x57]);
+        self.mark();
+    }
+
+    /// `pop r15`
+    pub fn pop_r15(&mut self) {
+        self.emit_bytes(&[0x41, 0x5F]);
+        self.mark();
+    }
+
+    /// Allocates an unbound label; jump to it with `jcc` before or after
+    /// calling `bind` on it.
+    pub fn label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Fixes `label`'s target to the current position. Nothing is patched
+    /// here: a `jcc` emitted after this call resolves its rel32 right away,
+    /// and `finalize` patches the ones emitted before it (forward jumps).
+    pub fn bind(&mut self, label: Label) {
+        self.labels[label.0] = Some(self.len());
+    }
+
+    /// Emits a conditional jump to `label`. If `label` is already bound
+    /// (a backward jump), the rel32 is resolved immediately; otherwise a
+    /// fixup is recorded for `finalize` to patch once the label is bound.
+    pub fn jcc(&mut self, cond: Cond, label: Label) {
+        let opcode = match cond {
+            Cond::Zero => [0x0F, 0x84],
+            Cond::NotZero => [0x0F, 0x85],
+            Cond::Below => [0x0F, 0x82],
+            Cond::AboveOrEqual => [0x0F, 0x83],
+        };
+        self.emit_bytes(&opcode);
+        let rel32_pos = self.len();
+        self.emit_u32(0);
+        match self.labels[label.0] {
+            Some(target) => {
+                let rel = target as i32 - (rel32_pos as i32 + 4);
+                self.replace_u32(rel32_pos, rel as u32);
+            }
+            None => self.fixups.push((rel32_pos, label)),
+        }
+        self.mark();
+    }
+
+    /// Patches every fixup left by forward jumps and returns the finished
+    /// code buffer.
+    pub fn finalize(mut self) -> Vec<u8> {
+        for (rel32_pos, label) in std::mem::take(&mut self.fixups) {
+            let target = self.labels[label.0].expect("label used by jcc was never bound");
+            let rel = target as i32 - (rel32_pos as i32 + 4);
+            self.replace_u32(rel32_pos, rel as u32);
+        }
+        self.content
+    }
+}
@@ -0,0 +1,265 @@
+mod assembler;
+
+use std::mem;
+
+use crate::ir::{BfOp, BfOpKind};
+
+use self::assembler::{Assembler, Cond, Label};
+use super::Backend;
+
+const TAPE_SIZE: usize = 500000;
+
+/// Trap code returned (packed into the low byte of the JIT's return value)
+/// when a memory-touching op would have read or written outside the tape.
+const TRAP_OUT_OF_BOUNDS: u32 = 1;
+
+/// Trap code for exceeding the `--max-steps` budget.
+const TRAP_STEP_LIMIT: u32 = 2;
+
+fn pack_trap(code: u32, op_index: usize) -> u32 {
+    code | ((op_index as u32) << 8)
+}
+
+fn unpack_trap(packed: i64) -> (u32, usize) {
+    let packed = packed as u32;
+    (packed & 0xFF, (packed >> 8) as usize)
+}
+
+/// Emits `cmp`/`jb`/`jae` against the tape's `[r12, r15)` bounds for the
+/// value currently in `r13`, trapping to `epilogue` on failure.
+fn check_r13_bounds(asm: &mut Assembler, epilogue: Label, op_index: usize) {
+    asm.mov_eax_imm32(pack_trap(TRAP_OUT_OF_BOUNDS, op_index));
+    asm.cmp_r13_r12();
+    asm.jcc(Cond::Below, epilogue);
+    asm.cmp_r13_r15();
+    asm.jcc(Cond::AboveOrEqual, epilogue);
+}
+
+/// Same as `check_r13_bounds`, but for the scratch pointer in `r14` that
+/// `LoopMoveData` computes for its destination cell.
+fn check_r14_bounds(asm: &mut Assembler, epilogue: Label, op_index: usize) {
+    asm.mov_eax_imm32(pack_trap(TRAP_OUT_OF_BOUNDS, op_index));
+    asm.cmp_r14_r12();
+    asm.jcc(Cond::Below, epilogue);
+    asm.cmp_r14_r15();
+    asm.jcc(Cond::AboveOrEqual, epilogue);
+}
+
+/// The byte range in the finished machine code that a single `BfOp` was
+/// compiled to, keyed by its index in the `ops` slice. Lets tooling (the
+/// `asm` listing, and potentially future debuggers) map code back to IR.
+struct OpRange {
+    op_index: usize,
+    start: usize,
+    end: usize,
+}
+
+fn compile(ops: &[BfOp], max_steps: Option<u64>) -> (Vec<u8>, Vec<OpRange>, Vec<usize>) {
+    let mut asm = Assembler::new();
+    let memory: *mut u8 = unsafe { mem::transmute(libc::malloc(TAPE_SIZE as libc::size_t)) };
+    let base = memory as u64;
+    // rbx/r12-r15 are callee-saved; save them now and restore before every
+    // `ret` below so the caller's values in them survive the call.
+    asm.push_rbx();
+    asm.push_r12();
+    asm.push_r13();
+    asm.push_r14();
+    asm.push_r15();
+    asm.mov_r13_imm64(base);
+    asm.mov_r12_imm64(base);
+    asm.mov_r15_imm64(base + TAPE_SIZE as u64);
+    if let Some(budget) = max_steps {
+        asm.mov_rbx_imm64(budget);
+    }
+
+    let epilogue = asm.label();
+    let mut bracket_stack: Vec<(Label, Label)> = vec![];
+    let mut op_ranges = Vec::with_capacity(ops.len());
+    for (i, op) in ops.iter().enumerate() {
+        let start = asm.len();
+        match op.kind {
+            BfOpKind::IncPtr => asm.add_r13_imm32(op.argument as u32),
+            BfOpKind::DecPtr => asm.sub_r13_imm32(op.argument as u32),
+            BfOpKind::IncData => {
+                check_r13_bounds(&mut asm, epilogue, i);
+                if op.argument < 256 {
+                    asm.add_byte_ptr_r13_imm8(op.argument as u8)
+                } else if op.argument < 65536 {
+                    asm.add_word_ptr_r13_imm16(op.argument as u16)
+                }
+            }
+            BfOpKind::DecData => {
+                check_r13_bounds(&mut asm, epilogue, i);
+                if op.argument < 256 {
+                    asm.sub_byte_ptr_r13_imm8(op.argument as u8)
+                } else if op.argument < 65536 {
+                    asm.sub_word_ptr_r13_imm16(op.argument as u16)
+                }
+            }
+            BfOpKind::WriteStdout => {
+                check_r13_bounds(&mut asm, epilogue, i);
+                asm.syscall_write()
+            }
+            BfOpKind::ReadStdin => {
+                check_r13_bounds(&mut asm, epilogue, i);
+                asm.syscall_read()
+            }
+            BfOpKind::LoopSetToZero => {
+                check_r13_bounds(&mut asm, epilogue, i);
+                asm.mov_byte_ptr_r13_zero()
+            }
+            BfOpKind::LoopMovePtr => {
+                let body = asm.label();
+                let end = asm.label();
+                check_r13_bounds(&mut asm, epilogue, i);
+                asm.cmp_byte_ptr_r13_zero();
+                asm.jcc(Cond::Zero, end);
+                asm.bind(body);
+                if op.argument >= 0 {
+                    asm.add_r13_imm32(op.argument as u32);
+                } else {
+                    asm.sub_r13_imm32(-op.argument as u32);
+                }
+                check_r13_bounds(&mut asm, epilogue, i);
+                asm.cmp_byte_ptr_r13_zero();
+                asm.jcc(Cond::NotZero, body);
+                asm.bind(end);
+            }
+            BfOpKind::LoopMoveData => {
+                let end = asm.label();
+                check_r13_bounds(&mut asm, epilogue, i);
+                asm.cmp_byte_ptr_r13_zero();
+                asm.jcc(Cond::Zero, end);
+                asm.mov_r14_r13();
+                if op.argument >= 0 {
+                    asm.add_r14_imm32(op.argument as u32);
+                } else {
+                    asm.sub_r14_imm32(-op.argument as u32);
+                }
+                check_r14_bounds(&mut asm, epilogue, i);
+                asm.movzx_rax_byte_ptr_r13();
+                asm.add_byte_ptr_r14_al();
+                asm.mov_byte_ptr_r13_zero();
+                asm.bind(end);
+            }
+            BfOpKind::JumpIfDataZero => {
+                let body = asm.label();
+                let end = asm.label();
+                check_r13_bounds(&mut asm, epilogue, i);
+                asm.cmp_byte_ptr_r13_zero();
+                asm.jcc(Cond::Zero, end);
+                asm.bind(body);
+                bracket_stack.push((body, end));
+            }
+            BfOpKind::JumpIfDataNotZero => {
+                let (body, end) = bracket_stack.pop().expect("mismatch [");
+                check_r13_bounds(&mut asm, epilogue, i);
+                if max_steps.is_some() {
+                    asm.mov_eax_imm32(pack_trap(TRAP_STEP_LIMIT, i));
+                    asm.sub_rbx_imm8(1);
+                    asm.jcc(Cond::Below, epilogue);
+                }
+                asm.cmp_byte_ptr_r13_zero();
+                asm.jcc(Cond::NotZero, body);
+                asm.bind(end);
+            }
+        }
+        op_ranges.push(OpRange {
+            op_index: i,
+            start,
+            end: asm.len(),
+        });
+    }
+    asm.mov_eax_imm32(0);
+    asm.pop_r15();
+    asm.pop_r14();
+    asm.pop_r13();
+    asm.pop_r12();
+    asm.pop_rbx();
+    asm.ret();
+    asm.bind(epilogue);
+    asm.pop_r15();
+    asm.pop_r14();
+    asm.pop_r13();
+    asm.pop_r12();
+    asm.pop_rbx();
+    asm.ret();
+    let instr_ends = asm.instruction_ends();
+    (asm.finalize(), op_ranges, instr_ends)
+}
+
+fn execute(code: &Vec<u8>) -> i64 {
+    unsafe {
+        let page = libc::mmap(
+            std::ptr::null_mut(),
+            code.len(),
+            libc::PROT_EXEC | libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_ANONYMOUS | libc::MAP_PRIVATE,
+            -1,
+            0,
+        );
+        let program: *mut u8 = mem::transmute(page);
+        program.copy_from_nonoverlapping(code.as_ptr(), code.len());
+        let f: fn() -> i64 = mem::transmute(page);
+        f()
+    }
+}
+
+/// Runs a program by JIT-compiling it to x86-64 machine code and jumping
+/// into it. Every memory-touching op is bounds-checked against the tape's
+/// allocation, so a runaway `>`/`<` traps instead of corrupting memory.
+/// Only works on x86-64 targets that support `mmap`'d executable pages,
+/// i.e. Linux.
+pub struct X64Backend;
+
+impl Backend for X64Backend {
+    fn run(&mut self, ops: &[BfOp], max_steps: Option<u64>) {
+        let (code, _, _) = compile(ops, max_steps);
+        let result = execute(&code);
+        if result != 0 {
+            let (code, op_index) = unpack_trap(result);
+            if code == TRAP_OUT_OF_BOUNDS {
+                eprintln!("error: tape pointer out of bounds at op {}", op_index);
+                std::process::exit(1);
+            } else if code == TRAP_STEP_LIMIT {
+                eprintln!("error: step limit exceeded at op {}", op_index);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Prints `code`, grouped by the `BfOp` each byte range came from, as an
+/// offset-prefixed hex listing. Each real x86-64 instruction (as tracked by
+/// the `Assembler` that emitted it) prints on one line; conditional jumps
+/// (`jcc rel32`) additionally get their resolved absolute target annotated.
+#[cfg(feature = "disasm")]
+pub fn print_asm(ops: &[BfOp]) {
+    let (code, ranges, instr_ends) = compile(ops, None);
+    let mut instr_ends = instr_ends.into_iter();
+    for range in &ranges {
+        let op = &ops[range.op_index];
+        println!("; op {:>4} {:?} argument={}", range.op_index, op.kind, op.argument);
+        let mut cursor = range.start;
+        while cursor < range.end {
+            let end = instr_ends
+                .find(|&end| end > cursor)
+                .expect("instruction boundaries cover the whole code buffer");
+            let chunk = &code[cursor..end];
+            let hex = chunk
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let is_jcc = chunk.len() == 6 && chunk[0] == 0x0F && matches!(chunk[1], 0x82 | 0x83 | 0x84 | 0x85);
+            if is_jcc {
+                let rel = i32::from_le_bytes([chunk[2], chunk[3], chunk[4], chunk[5]]);
+                let target = end as i64 + rel as i64;
+                println!("    {:06x}: {:<17} ; -> {:#06x}", cursor, hex, target);
+            } else {
+                println!("    {:06x}: {}", cursor, hex);
+            }
+            cursor = end;
+        }
+    }
+}
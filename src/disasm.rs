@@ -0,0 +1,9 @@
+use crate::ir::BfOp;
+
+/// Prints `ops` one per line, including the arguments the optimizer fused
+/// into `LoopSetToZero`/`LoopMovePtr`/`LoopMoveData`.
+pub fn print_ir(ops: &[BfOp]) {
+    for (i, op) in ops.iter().enumerate() {
+        println!("{:>4}: {:?} argument={}", i, op.kind, op.argument);
+    }
+}
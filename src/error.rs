@@ -0,0 +1,67 @@
+use std::fmt;
+
+/// Where a token sits in the source file, used to point diagnostics at the
+/// offending character.
+#[derive(Debug, Clone, Copy)]
+pub struct SourcePos {
+    pub offset: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug)]
+pub enum BfError {
+    Io(std::io::Error),
+    UnmatchedClose { pos: SourcePos },
+    UnclosedOpen { pos: SourcePos },
+}
+
+impl From<std::io::Error> for BfError {
+    fn from(err: std::io::Error) -> Self {
+        BfError::Io(err)
+    }
+}
+
+impl BfError {
+    /// Renders a rustc-style diagnostic. Positional errors get the
+    /// offending source line with a caret under the column it happened at;
+    /// `Io` just gets the underlying OS error message.
+    pub fn render(&self, filepath: &str) -> String {
+        let (message, pos) = match self {
+            BfError::Io(err) => return format!("error: {}: {}", filepath, err),
+            BfError::UnmatchedClose { pos } => ("unmatched ']'", *pos),
+            BfError::UnclosedOpen { pos } => ("unclosed '['", *pos),
+        };
+        let line_text = std::fs::read_to_string(filepath)
+            .ok()
+            .and_then(|contents| contents.lines().nth(pos.line - 1).map(str::to_string))
+            .unwrap_or_default();
+        format!(
+            "error: {} at line {}, col {} (byte offset {})\n  --> {}:{}:{}\n{:>4} | {}\n     | {}^",
+            message,
+            pos.line,
+            pos.col,
+            pos.offset,
+            filepath,
+            pos.line,
+            pos.col,
+            pos.line,
+            line_text,
+            " ".repeat(pos.col.saturating_sub(1)),
+        )
+    }
+}
+
+impl fmt::Display for BfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BfError::Io(err) => write!(f, "{}", err),
+            BfError::UnmatchedClose { pos } => {
+                write!(f, "unmatched ']' at line {}, col {}", pos.line, pos.col)
+            }
+            BfError::UnclosedOpen { pos } => {
+                write!(f, "unclosed '[' at line {}, col {}", pos.line, pos.col)
+            }
+        }
+    }
+}
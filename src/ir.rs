@@ -0,0 +1,185 @@
+use std::io::{BufRead, BufReader};
+
+use crate::error::{BfError, SourcePos};
+
+const TOKENS: &str = "><+-.,[]";
+
+/// A retained Brainfuck token together with where it sits in the source
+/// file, so later stages can point diagnostics at it.
+#[derive(Debug, Clone, Copy)]
+pub struct Token {
+    pub ch: char,
+    pub pos: SourcePos,
+}
+
+pub fn parse(filepath: &String) -> Result<Vec<Token>, BfError> {
+    let file = std::fs::File::open(filepath)?;
+    let reader = BufReader::new(file);
+    let mut res: Vec<Token> = vec![];
+    let mut offset = 0usize;
+
+    for (line_idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        for (col_idx, c) in line.chars().enumerate() {
+            if TOKENS.contains(c) {
+                res.push(Token {
+                    ch: c,
+                    pos: SourcePos {
+                        offset,
+                        line: line_idx + 1,
+                        col: col_idx + 1,
+                    },
+                });
+            }
+            offset += c.len_utf8();
+        }
+        offset += 1; // the newline BufRead::lines stripped off
+    }
+    Ok(res)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BfOpKind {
+    IncPtr,
+    DecPtr,
+    IncData,
+    DecData,
+    ReadStdin,
+    WriteStdout,
+    LoopSetToZero,
+    LoopMovePtr,
+    LoopMoveData,
+    JumpIfDataZero,
+    JumpIfDataNotZero,
+}
+
+#[derive(Debug)]
+pub struct BfOp {
+    pub kind: BfOpKind,
+    pub argument: i32,
+}
+
+pub fn translate(insts: &[Token]) -> Result<Vec<BfOp>, BfError> {
+    let mut res: Vec<BfOp> = vec![];
+    let mut loop_stack: Vec<(usize, SourcePos)> = vec![];
+    let mut pc: usize = 0;
+    let program_size = insts.len();
+    while pc < program_size {
+        let inst = insts[pc];
+        match inst.ch {
+            '[' => {
+                loop_stack.push((res.len(), inst.pos));
+                res.push(BfOp {
+                    kind: BfOpKind::JumpIfDataZero,
+                    argument: 0,
+                });
+                pc += 1;
+            }
+            ']' => {
+                let (offset, _) = loop_stack
+                    .pop()
+                    .ok_or(BfError::UnmatchedClose { pos: inst.pos })?;
+                let optimized_ops = optimize_loop(&res, offset);
+                if optimized_ops.len() == 0 {
+                    res[offset].argument = res.len() as i32;
+                    res.push(BfOp {
+                        kind: BfOpKind::JumpIfDataNotZero,
+                        argument: offset as i32,
+                    })
+                } else {
+                    res.splice(offset.., optimized_ops);
+                }
+                pc += 1;
+            }
+            _ => {
+                let num_repeats = insts[pc..insts.len()]
+                    .iter()
+                    .take_while(|t| t.ch == inst.ch)
+                    .count();
+                pc += num_repeats;
+                let kind = match inst.ch {
+                    '>' => BfOpKind::IncPtr,
+                    '<' => BfOpKind::DecPtr,
+                    '+' => BfOpKind::IncData,
+                    '-' => BfOpKind::DecData,
+                    ',' => BfOpKind::ReadStdin,
+                    '.' => BfOpKind::WriteStdout,
+                    _ => unreachable!("parse only retains Brainfuck tokens"),
+                };
+                res.push(BfOp {
+                    kind,
+                    argument: num_repeats as i32,
+                })
+            }
+        }
+    }
+    if let Some((_, pos)) = loop_stack.pop() {
+        return Err(BfError::UnclosedOpen { pos });
+    }
+    Ok(res)
+}
+
+fn optimize_loop(ops: &[BfOp], loop_start: usize) -> Vec<BfOp> {
+    let mut res: Vec<BfOp> = vec![];
+    let loop_size = ops.len() - loop_start;
+    match loop_size {
+        2 => {
+            let repeated_op = &ops[loop_start + 1];
+            match repeated_op.kind {
+                BfOpKind::IncData | BfOpKind::DecData => res.push(BfOp {
+                    kind: BfOpKind::LoopSetToZero,
+                    argument: 0,
+                }),
+                BfOpKind::IncPtr => res.push(BfOp {
+                    kind: BfOpKind::LoopMovePtr,
+                    argument: repeated_op.argument,
+                }),
+                BfOpKind::DecPtr => res.push(BfOp {
+                    kind: BfOpKind::LoopMovePtr,
+                    argument: -repeated_op.argument,
+                }),
+
+                _ => {}
+            }
+        }
+        5 => {
+            if ops[loop_start + 1].kind == BfOpKind::DecData
+                && ops[loop_start + 3].kind == BfOpKind::IncData
+                && ops[loop_start + 1].argument == 1
+                && ops[loop_start + 3].argument == 1
+            {
+                match (&ops[loop_start + 2], &ops[loop_start + 4]) {
+                    (
+                        BfOp {
+                            kind: BfOpKind::IncPtr,
+                            argument: a1,
+                        },
+                        BfOp {
+                            kind: BfOpKind::DecPtr,
+                            argument: a2,
+                        },
+                    ) if a1 == a2 => res.push(BfOp {
+                        kind: BfOpKind::LoopMoveData,
+                        argument: *a1,
+                    }),
+                    (
+                        BfOp {
+                            kind: BfOpKind::DecPtr,
+                            argument: a1,
+                        },
+                        BfOp {
+                            kind: BfOpKind::IncPtr,
+                            argument: a2,
+                        },
+                    ) if a1 == a2 => res.push(BfOp {
+                        kind: BfOpKind::LoopMoveData,
+                        argument: -*a1,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+    res
+}